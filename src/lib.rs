@@ -1,85 +1,374 @@
-use std::{
-    collections::{HashMap, LinkedList},
-    hash::Hash,
-    rc::Rc,
-};
+use std::{borrow::Borrow, collections::HashMap, hash::Hash, rc::Rc};
+
+mod sharded;
+
+pub use sharded::ShardedLru;
+
+/// A slot in the slab. `Value` slots form an intrusive doubly-linked list ordered by
+/// recency; `Free` slots form a singly-linked free list threaded through `LRU::free`.
+enum Node<K, V> {
+    Value {
+        key: Rc<K>,
+        value: Rc<V>,
+        weight: usize,
+        prev: Option<usize>,
+        next: Option<usize>,
+    },
+    Free {
+        next: Option<usize>,
+    },
+}
+
+type Weigher<K, V> = Box<dyn Fn(&K, &V) -> usize>;
 
 pub struct LRU<K, V> {
-    list: LinkedList<(Rc<K>, Rc<V>)>,
-    map: HashMap<Rc<K>, (Rc<V>, usize)>,
+    nodes: Vec<Node<K, V>>,
+    map: HashMap<K, usize>,
+    most_recent: Option<usize>,
+    least_recent: Option<usize>,
+    free: Option<usize>,
     num_items: usize,
     max_items: usize,
+    total_weight: usize,
+    max_weight: usize,
+    weigher: Option<Weigher<K, V>>,
 }
 
-impl<K: Eq + Hash, V> LRU<K, V> {
+impl<K: Eq + Hash + Clone, V> LRU<K, V> {
     pub fn new(max_items: usize) -> Self {
         Self {
-            list: LinkedList::new(),
+            nodes: Vec::new(),
             map: HashMap::new(),
+            most_recent: None,
+            least_recent: None,
+            free: None,
             num_items: 0,
             max_items,
+            total_weight: 0,
+            max_weight: usize::MAX,
+            weigher: None,
+        }
+    }
+
+    /// Bounds the cache by total weight (LevelDB calls this a "charge") instead of item count.
+    /// `weigher` computes the weight of each entry; `maybe_gc` (run by `push`) evicts
+    /// least-recently-used entries until `total_weight <= max_weight`.
+    pub fn with_weigher(max_weight: usize, weigher: impl Fn(&K, &V) -> usize + 'static) -> Self {
+        Self {
+            nodes: Vec::new(),
+            map: HashMap::new(),
+            most_recent: None,
+            least_recent: None,
+            free: None,
+            num_items: 0,
+            max_items: usize::MAX,
+            total_weight: 0,
+            max_weight,
+            weigher: Some(Box::new(weigher)),
+        }
+    }
+
+    fn weight_of(&self, key: &K, value: &V) -> usize {
+        self.weigher
+            .as_ref()
+            .map_or(0, |weigher| weigher(key, value))
+    }
+
+    /// Reclaims a free slot, or grows the slab if none is free.
+    fn alloc_node(&mut self, key: Rc<K>, value: Rc<V>, weight: usize) -> usize {
+        let node = Node::Value {
+            key,
+            value,
+            weight,
+            prev: None,
+            next: None,
+        };
+
+        if let Some(idx) = self.free {
+            let next_free = match &self.nodes[idx] {
+                Node::Free { next } => *next,
+                Node::Value { .. } => unreachable!("free list points at a live node"),
+            };
+
+            self.free = next_free;
+            self.nodes[idx] = node;
+
+            idx
+        } else {
+            self.nodes.push(node);
+
+            self.nodes.len() - 1
+        }
+    }
+
+    fn set_prev(&mut self, idx: usize, prev: Option<usize>) {
+        if let Node::Value { prev: p, .. } = &mut self.nodes[idx] {
+            *p = prev;
+        }
+    }
+
+    fn set_next(&mut self, idx: usize, next: Option<usize>) {
+        if let Node::Value { next: n, .. } = &mut self.nodes[idx] {
+            *n = next;
+        }
+    }
+
+    /// Links a detached node at the most-recently-used end.
+    fn link_most_recent(&mut self, idx: usize) {
+        self.set_prev(idx, None);
+        self.set_next(idx, self.most_recent);
+
+        if let Some(old_most_recent) = self.most_recent {
+            self.set_prev(old_most_recent, Some(idx));
+        }
+
+        self.most_recent = Some(idx);
+
+        if self.least_recent.is_none() {
+            self.least_recent = Some(idx);
+        }
+    }
+
+    /// Removes a node from the recency list without freeing its slot.
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = match &self.nodes[idx] {
+            Node::Value { prev, next, .. } => (*prev, *next),
+            Node::Free { .. } => unreachable!("unlinking a free slot"),
+        };
+
+        match prev {
+            Some(prev) => self.set_next(prev, next),
+            None => self.most_recent = next,
+        }
+
+        match next {
+            Some(next) => self.set_prev(next, prev),
+            None => self.least_recent = prev,
+        }
+    }
+
+    /// Moves a node to the most-recently-used end in O(1).
+    fn touch(&mut self, idx: usize) {
+        if self.most_recent == Some(idx) {
+            return;
+        }
+
+        self.unlink(idx);
+        self.link_most_recent(idx);
+    }
+
+    fn free_slot(&mut self, idx: usize) {
+        self.nodes[idx] = Node::Free { next: self.free };
+        self.free = Some(idx);
+    }
+
+    fn node_value(&self, idx: usize) -> Rc<V> {
+        match &self.nodes[idx] {
+            Node::Value { value, .. } => value.clone(),
+            Node::Free { .. } => unreachable!("map points at a free slot"),
         }
     }
 
     /// **Each key must map to exactly one value.** Pushing multiple different value for the same key is undefined behaviour.
     pub fn push(&mut self, key: Rc<K>, value: Rc<V>) -> Option<(Rc<K>, Rc<V>)> {
-        if let Some((_, count)) = self.map.get_mut(&key) {
+        if let Some(&idx) = self.map.get(key.as_ref()) {
             // value already in LRU
-            self.list.push_back((key, value));
-            *count += 1;
+            let weight = self.weight_of(&key, &value);
+
+            if let Node::Value {
+                value: v,
+                weight: w,
+                ..
+            } = &mut self.nodes[idx]
+            {
+                self.total_weight = self.total_weight - *w + weight;
+                *v = value;
+                *w = weight;
+            }
+
+            self.touch(idx);
 
             return self.maybe_gc();
         }
 
         // new element inserted
-        self.list.push_back((key.clone(), value.clone()));
-        self.map.insert(key, (value, 1));
+        let weight = self.weight_of(&key, &value);
+        let idx = self.alloc_node(key.clone(), value, weight);
+        self.link_most_recent(idx);
+        self.map.insert((*key).clone(), idx);
         self.num_items += 1;
+        self.total_weight += weight;
 
         self.maybe_gc()
     }
 
-    #[inline(always)]
+    /// Looks up `key`, marking the entry as most-recently-used so it survives the next `gc`.
+    ///
+    /// Lookups are generic over `Borrow`, so e.g. a `LRU<String, V>` can be queried with `&str`.
+    /// The map is keyed by owned `K`, so this resolves via the hash index in O(1) rather than
+    /// scanning every entry.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<Rc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = *self.map.get(key)?;
+
+        self.touch(idx);
+
+        Some(self.node_value(idx))
+    }
+
+    /// Looks up `key` without affecting recency.
+    pub fn peek<Q>(&self, key: &Q) -> Option<Rc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key).copied().map(|idx| self.node_value(idx))
+    }
+
+    fn over_capacity(&self) -> bool {
+        self.num_items > self.max_items || self.total_weight > self.max_weight
+    }
+
+    /// Evicts until the cache is back within capacity or nothing more can be evicted,
+    /// returning the last entry evicted, if any.
+    /// A single weighty push can overshoot `max_weight` by more than one entry's worth, so this
+    /// keeps calling [`Self::gc`] rather than evicting once and leaving the cache over capacity.
     pub fn maybe_gc(&mut self) -> Option<(Rc<K>, Rc<V>)> {
-        if self.num_items > self.max_items {
-            self.gc()
-        } else {
-            None
+        let mut last_evicted = None;
+
+        while self.over_capacity() {
+            match self.gc() {
+                Some(evicted) => last_evicted = Some(evicted),
+                None => break,
+            }
         }
+
+        last_evicted
     }
 
     pub fn gc(&mut self) -> Option<(Rc<K>, Rc<V>)> {
+        if !self.over_capacity() {
+            return None;
+        }
+
+        // a lone oversized entry must stay insertable: don't evict the last item
+        // just because its own weight exceeds max_weight
+        if self.weigher.is_some() && self.num_items <= 1 {
+            return None;
+        }
+
+        self.evict_coldest_unreferenced()
+    }
+
+    /// Unconditionally evicts and returns the coldest entry with no outstanding external
+    /// `Rc` references, skipping past (and re-touching) any colder entries that are still
+    /// referenced elsewhere. Shared by [`Self::gc`] and [`Self::pop_lru`].
+    fn evict_coldest_unreferenced(&mut self) -> Option<(Rc<K>, Rc<V>)> {
         let mut iterations = 0;
 
-        while iterations < self.list.len() && self.num_items > self.max_items {
+        while iterations < self.num_items {
             iterations += 1;
 
-            if let Some((key, value)) = self.list.pop_front() {
-                if let Some((_, count)) = self.map.get_mut(&key) {
-                    if *count > 1 {
-                        // multiple references exist in list
-                        *count -= 1;
-                    } else if Rc::strong_count(&value) > 2 {
-                        // a reference exists outside this LRU cache
-                        self.list.push_back((key, value));
-                    } else {
-                        // evict from cache
-                        self.map.remove(&key);
-                        self.num_items -= 1;
-                        // return the evicted pair
-                        return Some((key, value));
-                    }
-                } else {
-                    return Some((key, value));
-                }
-            } else {
-                // list is empty
-                return None;
+            let idx = self.least_recent?;
+
+            let (key, value, weight) = match &self.nodes[idx] {
+                Node::Value {
+                    key, value, weight, ..
+                } => (key.clone(), value.clone(), *weight),
+                Node::Free { .. } => unreachable!("least_recent points at a free slot"),
+            };
+
+            if Rc::strong_count(&value) > 2 {
+                // a reference exists outside this LRU cache; try the next-coldest entry
+                self.touch(idx);
+
+                continue;
             }
+
+            // evict from cache
+            self.unlink(idx);
+            self.free_slot(idx);
+            self.map.remove(key.as_ref());
+            self.num_items -= 1;
+            self.total_weight -= weight;
+
+            // return the evicted pair
+            return Some((key, value));
         }
 
         None
     }
+
+    /// Unconditionally evicts the coldest externally-unreferenced entry, regardless of
+    /// whether the cache is currently over capacity.
+    pub fn pop_lru(&mut self) -> Option<(Rc<K>, Rc<V>)> {
+        self.evict_coldest_unreferenced()
+    }
+
+    /// Removes `key`, if present, returning its value.
+    pub fn remove(&mut self, key: &K) -> Option<Rc<V>> {
+        let idx = *self.map.get(key)?;
+
+        let (value, weight) = match &self.nodes[idx] {
+            Node::Value { value, weight, .. } => (value.clone(), *weight),
+            Node::Free { .. } => unreachable!("map points at a free slot"),
+        };
+
+        self.unlink(idx);
+        self.free_slot(idx);
+        self.map.remove(key);
+        self.num_items -= 1;
+        self.total_weight -= weight;
+
+        Some(value)
+    }
+
+    /// Removes every entry, resetting the cache to empty.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.map.clear();
+        self.most_recent = None;
+        self.least_recent = None;
+        self.free = None;
+        self.num_items = 0;
+        self.total_weight = 0;
+    }
+
+    /// Iterates entries from least- to most-recently-used.
+    pub fn iter_lru(&self) -> IterLru<'_, K, V> {
+        IterLru {
+            lru: self,
+            current: self.least_recent,
+        }
+    }
+}
+
+/// Iterator over an [`LRU`]'s entries, from least- to most-recently-used. See [`LRU::iter_lru`].
+pub struct IterLru<'a, K, V> {
+    lru: &'a LRU<K, V>,
+    current: Option<usize>,
+}
+
+impl<K, V> Iterator for IterLru<'_, K, V> {
+    type Item = (Rc<K>, Rc<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.current?;
+
+        match &self.lru.nodes[idx] {
+            Node::Value {
+                key, value, prev, ..
+            } => {
+                self.current = *prev;
+
+                Some((key.clone(), value.clone()))
+            }
+            Node::Free { .. } => unreachable!("recency list points at a free slot"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -89,12 +378,7 @@ mod tests {
 
     #[test]
     fn test_basic_insertion_and_eviction() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 3,
-        };
+        let mut lru = LRU::new(3);
 
         // Insert elements
         assert!(lru.push(Rc::new(1), Rc::new("a")).is_none());
@@ -110,19 +394,14 @@ mod tests {
 
         // Check internal state
         assert_eq!(lru.num_items, 3);
-        assert!(lru.map.contains_key(&Rc::new(2)));
-        assert!(lru.map.contains_key(&Rc::new(3)));
-        assert!(lru.map.contains_key(&Rc::new(4)));
+        assert!(lru.map.contains_key(&2));
+        assert!(lru.map.contains_key(&3));
+        assert!(lru.map.contains_key(&4));
     }
 
     #[test]
     fn test_reinsertion_of_existing_key() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 3,
-        };
+        let mut lru = LRU::new(3);
 
         let key = Rc::new(1);
         let value = Rc::new("a");
@@ -140,19 +419,14 @@ mod tests {
 
         // Check internal state
         assert_eq!(lru.num_items, 3);
-        assert!(lru.map.contains_key(&key));
-        assert!(lru.map.contains_key(&Rc::new(3)));
-        assert!(lru.map.contains_key(&Rc::new(4)));
+        assert!(lru.map.contains_key(key.as_ref()));
+        assert!(lru.map.contains_key(&3));
+        assert!(lru.map.contains_key(&4));
     }
 
     #[test]
     fn test_gc_with_reference_counts() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 2,
-        };
+        let mut lru = LRU::new(2);
 
         let key1 = Rc::new(1);
         let value1 = Rc::new("a");
@@ -176,25 +450,24 @@ mod tests {
         // Drop external reference to value2
         drop(value2);
 
-        // Add another element, this should evict key2/value2
+        // Adding another element now frees up key2/value2, but the cache is still over
+        // capacity afterwards (key1 and key3 are both pinned by external refs), so gc keeps
+        // evicting until it converges — even evicting the entry just pushed, since it's the
+        // only unpinned one left
         let evicted = lru.push(Rc::new(4), Rc::new("d")).unwrap();
-        assert_eq!(*evicted.0, 2); // Key "2" should be evicted
+        assert_eq!(*evicted.0, 4);
 
         // Check internal state
-        assert_eq!(lru.num_items, 3);
-        assert!(lru.map.contains_key(&Rc::new(1)));
-        assert!(lru.map.contains_key(&Rc::new(3)));
-        assert!(lru.map.contains_key(&Rc::new(4)));
+        assert_eq!(lru.num_items, 2);
+        assert!(lru.map.contains_key(&1));
+        assert!(!lru.map.contains_key(&2));
+        assert!(lru.map.contains_key(&3));
+        assert!(!lru.map.contains_key(&4));
     }
 
     #[test]
     fn test_handling_empty_lru() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 2,
-        };
+        let mut lru = LRU::new(2);
 
         // Try GC on empty LRU
         let evicted = lru.gc();
@@ -220,7 +493,7 @@ mod tests {
         let evicted = lru.push(key.clone(), value.clone());
         assert!(evicted.is_none());
         assert_eq!(lru.num_items, 1);
-        assert!(lru.map.contains_key(&key));
+        assert!(lru.map.contains_key(key.as_ref()));
     }
 
     #[test]
@@ -242,8 +515,8 @@ mod tests {
         assert_eq!(*evicted_key, 1);
         assert_eq!(*evicted_value, "a");
         assert_eq!(lru.num_items, 2);
-        assert!(lru.map.contains_key(&key2));
-        assert!(lru.map.contains_key(&key3));
+        assert!(lru.map.contains_key(key2.as_ref()));
+        assert!(lru.map.contains_key(key3.as_ref()));
     }
 
     #[test]
@@ -256,14 +529,17 @@ mod tests {
             let key_ref = key.clone();
             let value_ref = value.clone();
             lru.push(key_ref, value_ref);
-            assert_eq!(Rc::strong_count(&key), 3);
-            assert_eq!(Rc::strong_count(&value), 3);
+            // the node holds the only stored clone of the key; the map holds an owned copy,
+            // not an `Rc` clone, so it doesn't add to the count
+            assert_eq!(Rc::strong_count(&key), 2);
+            // the node holds the only stored clone of the value
+            assert_eq!(Rc::strong_count(&value), 2);
         }
 
         let evicted = lru.push(key.clone(), value.clone());
         assert!(evicted.is_none());
-        assert_eq!(Rc::strong_count(&key), 4);
-        assert_eq!(Rc::strong_count(&value), 4);
+        assert_eq!(Rc::strong_count(&key), 2);
+        assert_eq!(Rc::strong_count(&value), 2);
     }
 
     #[test]
@@ -273,43 +549,35 @@ mod tests {
         assert!(evicted.is_none());
     }
 
-    fn setup_lru(max_items: usize) -> LRU<i32, i32> {
-        LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items,
-        }
-    }
-
     #[test]
     fn test_push_new_element() {
-        let mut lru = setup_lru(2);
+        let mut lru = LRU::new(2);
         let key = Rc::new(1);
         let value = Rc::new(10);
 
         assert_eq!(lru.push(Rc::clone(&key), Rc::clone(&value)), None);
-        assert_eq!(lru.list.len(), 1);
+        assert_eq!(lru.nodes.len(), 1);
         assert_eq!(lru.map.len(), 1);
         assert_eq!(lru.num_items, 1);
     }
 
     #[test]
     fn test_push_existing_element() {
-        let mut lru = setup_lru(2);
+        let mut lru = LRU::new(2);
         let key = Rc::new(1);
         let value = Rc::new(10);
 
         lru.push(Rc::clone(&key), Rc::clone(&value));
         assert_eq!(lru.push(Rc::clone(&key), Rc::clone(&value)), None);
-        assert_eq!(lru.list.len(), 2);
+        // re-pushing an existing key must not grow the slab
+        assert_eq!(lru.nodes.len(), 1);
         assert_eq!(lru.map.len(), 1);
         assert_eq!(lru.num_items, 1);
     }
 
     #[test]
     fn test_gc_multiple_references() {
-        let mut lru = setup_lru(2);
+        let mut lru = LRU::new(2);
         let key1 = Rc::new(1);
         let value1 = Rc::new(10);
         let key2 = Rc::new(2);
@@ -320,19 +588,14 @@ mod tests {
         lru.push(Rc::clone(&key1), Rc::clone(&value1)); // key1 is pushed again
 
         assert_eq!(lru.gc(), None); // No eviction should happen
-        assert_eq!(lru.list.len(), 3);
+        assert_eq!(lru.nodes.len(), 2);
         assert_eq!(lru.map.len(), 2);
         assert_eq!(lru.num_items, 2);
     }
 
     #[test]
     fn test_push_and_retrieve() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 3,
-        };
+        let mut lru = LRU::new(3);
 
         let key1 = Rc::new(1);
         let value1 = Rc::new("a");
@@ -346,19 +609,14 @@ mod tests {
         lru.push(key3.clone(), value3.clone());
 
         assert_eq!(lru.num_items, 3);
-        assert!(lru.map.contains_key(&key1));
-        assert!(lru.map.contains_key(&key2));
-        assert!(lru.map.contains_key(&key3));
+        assert!(lru.map.contains_key(key1.as_ref()));
+        assert!(lru.map.contains_key(key2.as_ref()));
+        assert!(lru.map.contains_key(key3.as_ref()));
     }
 
     #[test]
     fn test_eviction_policy() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 2,
-        };
+        let mut lru = LRU::new(2);
 
         let key1 = Rc::new(1);
         let value1 = Rc::new("a");
@@ -372,20 +630,15 @@ mod tests {
         let evicted = lru.push(key3.clone(), value3.clone());
 
         assert_eq!(lru.num_items, 3);
-        assert!(lru.map.contains_key(&key1));
-        assert!(lru.map.contains_key(&key2));
-        assert!(lru.map.contains_key(&key3));
+        assert!(lru.map.contains_key(key1.as_ref()));
+        assert!(lru.map.contains_key(key2.as_ref()));
+        assert!(lru.map.contains_key(key3.as_ref()));
         assert_eq!(evicted, None);
     }
 
     #[test]
     fn test_multiple_references() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 2,
-        };
+        let mut lru = LRU::new(2);
 
         let key1 = Rc::new(1);
         let value1 = Rc::new("a");
@@ -393,19 +646,15 @@ mod tests {
         lru.push(key1.clone(), value1.clone());
         lru.push(key1.clone(), value1.clone());
 
+        // re-pushing the same key keeps a single slab slot
         assert_eq!(lru.num_items, 1);
-        assert_eq!(lru.list.len(), 2);
-        assert_eq!(lru.map.get(&key1).unwrap().1, 2);
+        assert_eq!(lru.nodes.len(), 1);
+        assert_eq!(lru.map.len(), 1);
     }
 
     #[test]
     fn test_eviction_with_external_references() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 1,
-        };
+        let mut lru = LRU::new(1);
 
         let key1 = Rc::new(1);
         let value1 = Rc::new("a");
@@ -416,18 +665,13 @@ mod tests {
         let evicted = lru.push(key2.clone(), value2.clone());
 
         assert_eq!(lru.num_items, 1);
-        assert!(lru.map.contains_key(&key2));
+        assert!(lru.map.contains_key(key2.as_ref()));
         assert_eq!(evicted, Some((Rc::new(1), Rc::new("a"))));
     }
 
     #[test]
     fn test_gc_behavior() {
-        let mut lru = LRU {
-            list: LinkedList::new(),
-            map: HashMap::new(),
-            num_items: 0,
-            max_items: 1,
-        };
+        let mut lru = LRU::new(1);
 
         let key1 = Rc::new(1);
         let value1 = Rc::new("a");
@@ -441,8 +685,8 @@ mod tests {
 
         assert!(gc_result.is_none());
         assert_eq!(lru.num_items, 2);
-        assert!(lru.map.contains_key(&key1));
-        assert!(lru.map.contains_key(&key2));
+        assert!(lru.map.contains_key(key1.as_ref()));
+        assert!(lru.map.contains_key(key2.as_ref()));
     }
 
     #[test]
@@ -499,4 +743,275 @@ mod tests {
         assert!(evicted.is_some());
         assert_eq!(*evicted.unwrap().0, 1);
     }
+
+    #[test]
+    fn test_get_returns_value_and_updates_recency() {
+        let mut lru = LRU::new(2);
+        let k1 = Rc::new(1);
+        let v1 = Rc::new("a");
+        let k2 = Rc::new(2);
+
+        lru.push(k1.clone(), v1.clone());
+        lru.push(k2.clone(), Rc::new("b"));
+
+        // touch key1 so key2 becomes the coldest entry
+        assert_eq!(lru.get(&1), Some(v1));
+
+        let evicted = lru.push(Rc::new(3), Rc::new("c")).unwrap();
+
+        assert_eq!(*evicted.0, 2); // key2 evicted, not key1
+        assert!(lru.map.contains_key(k1.as_ref()));
+    }
+
+    #[test]
+    fn test_get_missing_key() {
+        let mut lru = LRU::<i32, &str>::new(2);
+        lru.push(Rc::new(1), Rc::new("a"));
+
+        assert_eq!(lru.get(&42), None);
+    }
+
+    #[test]
+    fn test_get_by_borrowed_str_key() {
+        let mut lru = LRU::<String, i32>::new(2);
+        lru.push(Rc::new("hello".to_string()), Rc::new(1));
+
+        assert_eq!(lru.get("hello"), Some(Rc::new(1)));
+    }
+
+    #[test]
+    fn test_peek_does_not_update_recency() {
+        let mut lru = LRU::new(2);
+        let k1 = Rc::new(1);
+        let v1 = Rc::new("a");
+        let k2 = Rc::new(2);
+        let v2 = Rc::new("b");
+
+        lru.push(k1.clone(), v1.clone());
+        lru.push(k2.clone(), v2);
+
+        assert_eq!(lru.peek(&1), Some(v1));
+
+        let k3 = Rc::new(3);
+        let v3 = Rc::new("c");
+        let evicted = lru.push(k3, v3).unwrap();
+
+        // peek must not have protected key1 from eviction
+        assert_eq!(*evicted.0, 1);
+        assert!(lru.map.contains_key(k2.as_ref()));
+    }
+
+    #[test]
+    fn test_repush_does_not_grow_slab_across_many_cycles() {
+        let mut lru = LRU::new(2);
+        let key = Rc::new(1);
+        let value = Rc::new("a");
+
+        for _ in 0..100 {
+            lru.push(key.clone(), value.clone());
+        }
+
+        assert_eq!(lru.nodes.len(), 1);
+        assert_eq!(lru.num_items, 1);
+    }
+
+    #[test]
+    fn test_free_slot_is_reused_after_eviction() {
+        let mut lru = LRU::new(1);
+
+        lru.push(Rc::new(1), Rc::new("a"));
+        lru.push(Rc::new(2), Rc::new("b")); // evicts key 1, freeing a slot
+        lru.push(Rc::new(3), Rc::new("c")); // reuses the freed slot instead of growing the slab
+
+        assert_eq!(lru.nodes.len(), 2);
+        assert_eq!(lru.num_items, 1);
+        assert!(lru.map.contains_key(&3));
+    }
+
+    #[test]
+    fn test_weigher_evicts_by_total_weight() {
+        // capacity for 5 "units"; each entry's weight is its string length
+        let mut lru = LRU::with_weigher(5, |_key: &i32, value: &&str| value.len());
+
+        lru.push(Rc::new(1), Rc::new("abc")); // weight 3, total 3
+        lru.push(Rc::new(2), Rc::new("de")); // weight 2, total 5
+
+        assert_eq!(lru.total_weight, 5);
+        assert!(lru.map.contains_key(&1));
+        assert!(lru.map.contains_key(&2));
+
+        // pushing "fgh" (weight 3) brings total_weight to 8, over the cap of 5
+        let evicted = lru.push(Rc::new(3), Rc::new("fgh")).unwrap();
+
+        assert_eq!(*evicted.0, 1); // coldest entry evicted first
+        assert_eq!(lru.total_weight, 5);
+        assert!(!lru.map.contains_key(&1));
+        assert!(lru.map.contains_key(&2));
+        assert!(lru.map.contains_key(&3));
+    }
+
+    #[test]
+    fn test_weigher_evicts_multiple_entries_for_one_push() {
+        // capacity for 5 "units"; five weight-1 entries fill it exactly
+        let mut lru = LRU::with_weigher(5, |_key: &i32, value: &&str| value.len());
+
+        for key in 1..=5 {
+            lru.push(Rc::new(key), Rc::new("a"));
+        }
+
+        assert_eq!(lru.total_weight, 5);
+
+        // this single weight-5 entry alone brings total_weight to 10; one eviction only
+        // reclaims 1 unit, so gc must keep evicting the coldest entries until it fits
+        let evicted = lru.push(Rc::new(6), Rc::new("fghij"));
+
+        assert!(evicted.is_some());
+        assert!(lru.total_weight <= 5);
+        assert_eq!(lru.total_weight, 5);
+        assert!(lru.map.contains_key(&6));
+        for key in 1..=5 {
+            assert!(!lru.map.contains_key(&key));
+        }
+    }
+
+    #[test]
+    fn test_weigher_allows_oversized_single_entry() {
+        let mut lru = LRU::with_weigher(2, |_key: &i32, value: &&str| value.len());
+
+        // this single entry's weight (5) alone exceeds max_weight (2)
+        let evicted = lru.push(Rc::new(1), Rc::new("abcde"));
+
+        assert!(evicted.is_none());
+        assert_eq!(lru.num_items, 1);
+        assert_eq!(lru.total_weight, 5);
+        assert!(lru.map.contains_key(&1));
+    }
+
+    #[test]
+    fn test_weigher_updates_weight_on_repush() {
+        let mut lru = LRU::with_weigher(10, |_key: &i32, value: &&str| value.len());
+        let key = Rc::new(1);
+
+        lru.push(key.clone(), Rc::new("ab"));
+        assert_eq!(lru.total_weight, 2);
+
+        lru.push(key, Rc::new("abcd"));
+        assert_eq!(lru.total_weight, 4);
+    }
+
+    #[test]
+    fn test_item_count_mode_ignores_weight() {
+        // without a weigher, total_weight stays 0 and capacity is governed by item count
+        let mut lru = LRU::new(2);
+
+        lru.push(Rc::new(1), Rc::new("a"));
+        lru.push(Rc::new(2), Rc::new("b"));
+
+        assert_eq!(lru.total_weight, 0);
+        assert_eq!(lru.max_weight, usize::MAX);
+    }
+
+    #[test]
+    fn test_iter_lru_orders_least_to_most_recently_used() {
+        let mut lru = LRU::new(3);
+
+        lru.push(Rc::new(1), Rc::new("a"));
+        lru.push(Rc::new(2), Rc::new("b"));
+        lru.push(Rc::new(3), Rc::new("c"));
+
+        // touch key1 so it becomes the most-recently-used
+        lru.get(&1);
+
+        let order: Vec<i32> = lru.iter_lru().map(|(k, _)| *k).collect();
+        assert_eq!(order, vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn test_iter_lru_empty() {
+        let lru = LRU::<i32, &str>::new(3);
+        assert_eq!(lru.iter_lru().count(), 0);
+    }
+
+    #[test]
+    fn test_pop_lru_evicts_coldest_unconditionally() {
+        let mut lru = LRU::new(10); // well under capacity
+
+        lru.push(Rc::new(1), Rc::new("a"));
+        lru.push(Rc::new(2), Rc::new("b"));
+
+        let popped = lru.pop_lru().unwrap();
+        assert_eq!(*popped.0, 1);
+        assert_eq!(lru.num_items, 1);
+        assert!(lru.map.contains_key(&2));
+    }
+
+    #[test]
+    fn test_pop_lru_skips_externally_referenced_entries() {
+        let mut lru = LRU::new(10);
+
+        let key1 = Rc::new(1);
+        let value1 = Rc::new("a");
+        lru.push(key1.clone(), value1.clone());
+        lru.push(Rc::new(2), Rc::new("b"));
+
+        // keep key1/value1 alive externally so pop_lru must skip past them
+        let popped = lru.pop_lru().unwrap();
+        assert_eq!(*popped.0, 2);
+        assert!(lru.map.contains_key(key1.as_ref()));
+    }
+
+    #[test]
+    fn test_pop_lru_on_empty_lru() {
+        let mut lru = LRU::<i32, &str>::new(3);
+        assert_eq!(lru.pop_lru(), None);
+    }
+
+    #[test]
+    fn test_clear_removes_everything() {
+        let mut lru = LRU::new(3);
+
+        lru.push(Rc::new(1), Rc::new("a"));
+        lru.push(Rc::new(2), Rc::new("b"));
+
+        lru.clear();
+
+        assert_eq!(lru.num_items, 0);
+        assert_eq!(lru.total_weight, 0);
+        assert_eq!(lru.iter_lru().count(), 0);
+        assert_eq!(lru.get(&1), None);
+    }
+
+    #[test]
+    fn test_remove_existing_key() {
+        let mut lru = LRU::new(3);
+
+        lru.push(Rc::new(1), Rc::new("a"));
+        lru.push(Rc::new(2), Rc::new("b"));
+
+        assert_eq!(lru.remove(&1), Some(Rc::new("a")));
+        assert_eq!(lru.num_items, 1);
+        assert!(!lru.map.contains_key(&1));
+        assert!(lru.map.contains_key(&2));
+    }
+
+    #[test]
+    fn test_remove_missing_key() {
+        let mut lru = LRU::<i32, &str>::new(3);
+        lru.push(Rc::new(1), Rc::new("a"));
+
+        assert_eq!(lru.remove(&42), None);
+        assert_eq!(lru.num_items, 1);
+    }
+
+    #[test]
+    fn test_remove_then_reinsert_reuses_freed_slot() {
+        let mut lru = LRU::new(3);
+
+        lru.push(Rc::new(1), Rc::new("a"));
+        lru.remove(&1);
+        lru.push(Rc::new(2), Rc::new("b"));
+
+        assert_eq!(lru.nodes.len(), 1);
+        assert_eq!(lru.num_items, 1);
+    }
 }
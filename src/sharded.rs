@@ -0,0 +1,342 @@
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+/// Number of independent shards. Each shard is guarded by its own lock, so lock
+/// contention is spread across shards instead of serializing on a single mutex.
+const NUM_SHARDS: usize = 16;
+
+/// A slot in a shard's slab, mirroring [`crate::LRU`] but built on `Arc` so shards
+/// can be shared across threads.
+enum Node<K, V> {
+    Value {
+        key: Arc<K>,
+        value: Arc<V>,
+        prev: Option<usize>,
+        next: Option<usize>,
+    },
+    Free {
+        next: Option<usize>,
+    },
+}
+
+/// A single `Arc`-based, `Mutex`-guarded LRU shard.
+struct Shard<K, V> {
+    nodes: Vec<Node<K, V>>,
+    map: HashMap<K, usize>,
+    most_recent: Option<usize>,
+    least_recent: Option<usize>,
+    free: Option<usize>,
+    num_items: usize,
+    max_items: usize,
+}
+
+impl<K: Eq + Hash + Clone, V> Shard<K, V> {
+    fn new(max_items: usize) -> Self {
+        Self {
+            nodes: Vec::new(),
+            map: HashMap::new(),
+            most_recent: None,
+            least_recent: None,
+            free: None,
+            num_items: 0,
+            max_items,
+        }
+    }
+
+    fn alloc_node(&mut self, key: Arc<K>, value: Arc<V>) -> usize {
+        let node = Node::Value {
+            key,
+            value,
+            prev: None,
+            next: None,
+        };
+
+        if let Some(idx) = self.free {
+            let next_free = match &self.nodes[idx] {
+                Node::Free { next } => *next,
+                Node::Value { .. } => unreachable!("free list points at a live node"),
+            };
+
+            self.free = next_free;
+            self.nodes[idx] = node;
+
+            idx
+        } else {
+            self.nodes.push(node);
+
+            self.nodes.len() - 1
+        }
+    }
+
+    fn set_prev(&mut self, idx: usize, prev: Option<usize>) {
+        if let Node::Value { prev: p, .. } = &mut self.nodes[idx] {
+            *p = prev;
+        }
+    }
+
+    fn set_next(&mut self, idx: usize, next: Option<usize>) {
+        if let Node::Value { next: n, .. } = &mut self.nodes[idx] {
+            *n = next;
+        }
+    }
+
+    fn link_most_recent(&mut self, idx: usize) {
+        self.set_prev(idx, None);
+        self.set_next(idx, self.most_recent);
+
+        if let Some(old_most_recent) = self.most_recent {
+            self.set_prev(old_most_recent, Some(idx));
+        }
+
+        self.most_recent = Some(idx);
+
+        if self.least_recent.is_none() {
+            self.least_recent = Some(idx);
+        }
+    }
+
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = match &self.nodes[idx] {
+            Node::Value { prev, next, .. } => (*prev, *next),
+            Node::Free { .. } => unreachable!("unlinking a free slot"),
+        };
+
+        match prev {
+            Some(prev) => self.set_next(prev, next),
+            None => self.most_recent = next,
+        }
+
+        match next {
+            Some(next) => self.set_prev(next, prev),
+            None => self.least_recent = prev,
+        }
+    }
+
+    fn touch(&mut self, idx: usize) {
+        if self.most_recent == Some(idx) {
+            return;
+        }
+
+        self.unlink(idx);
+        self.link_most_recent(idx);
+    }
+
+    fn free_slot(&mut self, idx: usize) {
+        self.nodes[idx] = Node::Free { next: self.free };
+        self.free = Some(idx);
+    }
+
+    fn node_value(&self, idx: usize) -> Arc<V> {
+        match &self.nodes[idx] {
+            Node::Value { value, .. } => value.clone(),
+            Node::Free { .. } => unreachable!("map points at a free slot"),
+        }
+    }
+
+    fn push(&mut self, key: Arc<K>, value: Arc<V>) -> Option<(Arc<K>, Arc<V>)> {
+        if let Some(&idx) = self.map.get(key.as_ref()) {
+            if let Node::Value { value: v, .. } = &mut self.nodes[idx] {
+                *v = value;
+            }
+
+            self.touch(idx);
+
+            return self.maybe_gc();
+        }
+
+        let idx = self.alloc_node(key.clone(), value);
+        self.link_most_recent(idx);
+        self.map.insert((*key).clone(), idx);
+        self.num_items += 1;
+
+        self.maybe_gc()
+    }
+
+    /// Looks up `key`, marking the entry as most-recently-used. The map is keyed by owned `K`,
+    /// so this resolves through the hash index in O(1) rather than scanning every entry.
+    fn get<Q>(&mut self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = *self.map.get(key)?;
+
+        self.touch(idx);
+
+        Some(self.node_value(idx))
+    }
+
+    #[inline(always)]
+    fn maybe_gc(&mut self) -> Option<(Arc<K>, Arc<V>)> {
+        if self.num_items > self.max_items {
+            self.gc()
+        } else {
+            None
+        }
+    }
+
+    fn gc(&mut self) -> Option<(Arc<K>, Arc<V>)> {
+        let mut iterations = 0;
+
+        while iterations < self.num_items && self.num_items > self.max_items {
+            iterations += 1;
+
+            let idx = self.least_recent?;
+
+            let (key, value) = match &self.nodes[idx] {
+                Node::Value { key, value, .. } => (key.clone(), value.clone()),
+                Node::Free { .. } => unreachable!("least_recent points at a free slot"),
+            };
+
+            if Arc::strong_count(&value) > 2 {
+                // a reference exists outside this cache; try the next-coldest entry
+                self.touch(idx);
+
+                continue;
+            }
+
+            self.unlink(idx);
+            self.free_slot(idx);
+            self.map.remove(key.as_ref());
+            self.num_items -= 1;
+
+            return Some((key, value));
+        }
+
+        None
+    }
+}
+
+/// A thread-safe LRU cache, sharded [LevelDB-style](https://github.com/google/leveldb/blob/main/util/cache.cc)
+/// across `NUM_SHARDS` independently-locked [`Shard`]s so concurrent readers and writers on
+/// different keys don't contend on a single lock.
+pub struct ShardedLru<K, V> {
+    shards: Vec<Mutex<Shard<K, V>>>,
+}
+
+impl<K: Eq + Hash + Clone, V> ShardedLru<K, V> {
+    pub fn new(max_items: usize) -> Self {
+        let per_shard = (max_items / NUM_SHARDS).max(1);
+
+        let shards = (0..NUM_SHARDS)
+            .map(|_| Mutex::new(Shard::new(per_shard)))
+            .collect();
+
+        Self { shards }
+    }
+
+    fn shard_index<Q>(&self, key: &Q) -> usize
+    where
+        Q: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+
+        (hasher.finish() as usize) % self.shards.len()
+    }
+
+    pub fn push(&self, key: Arc<K>, value: Arc<V>) -> Option<(Arc<K>, Arc<V>)> {
+        let shard = &self.shards[self.shard_index(&*key)];
+
+        shard.lock().unwrap().push(key, value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let shard = &self.shards[self.shard_index(key)];
+
+        shard.lock().unwrap().get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().num_items)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_get() {
+        let lru = ShardedLru::new(32);
+
+        lru.push(Arc::new(1), Arc::new("a"));
+        lru.push(Arc::new(2), Arc::new("b"));
+
+        assert_eq!(lru.get(&1), Some(Arc::new("a")));
+        assert_eq!(lru.get(&2), Some(Arc::new("b")));
+        assert_eq!(lru.get(&3), None);
+        assert_eq!(lru.len(), 2);
+    }
+
+    #[test]
+    fn test_eviction_across_shards() {
+        // max_items=1 clamps every one of the 16 shards to a capacity of 1 item,
+        // so no matter how the hash spreads 100 distinct keys, at most 16 survive
+        let lru = ShardedLru::new(1);
+
+        for key in 0..100 {
+            lru.push(Arc::new(key), Arc::new(key * 2));
+        }
+
+        assert!(lru.len() <= NUM_SHARDS);
+        assert!(!lru.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_access_across_threads() {
+        use std::thread;
+
+        // capacity is generous relative to the item count so eviction doesn't
+        // kick in even if the hash distributes keys unevenly across shards
+        let lru = Arc::new(ShardedLru::new(100_000));
+        let mut handles = Vec::new();
+
+        for t in 0..8 {
+            let lru = lru.clone();
+
+            handles.push(thread::spawn(move || {
+                for i in 0..100 {
+                    let key = t * 100 + i;
+                    lru.push(Arc::new(key), Arc::new(key * 2));
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(lru.len(), 800);
+
+        for t in 0..8 {
+            for i in 0..100 {
+                let key = t * 100 + i;
+                assert_eq!(lru.get(&key), Some(Arc::new(key * 2)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_cache() {
+        let lru = ShardedLru::<i32, i32>::new(16);
+
+        assert!(lru.is_empty());
+        assert_eq!(lru.get(&1), None);
+    }
+}